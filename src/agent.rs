@@ -0,0 +1,243 @@
+//! System-wide agent mode.
+//!
+//! Normally this crate only themes the window it creates itself. Agent
+//! mode instead watches every top-level window shown or brought to the
+//! foreground anywhere on the desktop — via an out-of-context
+//! `SetWinEventHook` — and applies this crate's dark-mode + backdrop
+//! effects to it, picking the effect from a small rule table matched by
+//! window class or owning executable name. This turns the demo into a
+//! resident tool that themes arbitrary windows.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, OnceLock};
+
+use log::*;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, HWND};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::backdrop::{apply_backdrop, BackdropKind};
+use crate::{enable_dark_mode, IS_DARK_MODE};
+
+// HWNDs already themed this run, so `EVENT_OBJECT_SHOW` doesn't re-theme
+// on every repaint. Entries are evicted on `EVENT_OBJECT_DESTROY` /
+// `EVENT_OBJECT_HIDE` so the set doesn't grow unbounded and so a HWND
+// value recycled by Windows for a brand-new window isn't mistaken for
+// one already seen.
+static SEEN: OnceLock<Mutex<HashSet<isize>>> = OnceLock::new();
+static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+
+fn seen() -> &'static Mutex<HashSet<isize>> {
+    SEEN.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// One entry in the theming rule table: match a top-level window by
+/// class name or owning executable, then apply a backdrop + dark/light
+/// choice to it. The first matching rule wins; a rule with neither
+/// matcher set acts as the catch-all default.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub match_class: Option<String>,
+    pub match_exe: Option<String>,
+    pub backdrop: BackdropKind,
+    pub dark: bool,
+}
+
+fn default_rules() -> Vec<Rule> {
+    vec![Rule {
+        match_class: None,
+        match_exe: None,
+        backdrop: BackdropKind::Auto,
+        dark: IS_DARK_MODE.load(Ordering::Relaxed),
+    }]
+}
+
+/// Parse the rule file format: one `class:Name=backdrop,dark` or
+/// `exe:name.exe=backdrop,light` rule per line, `#` comments, blank
+/// lines ignored. A missing or unparseable file falls back to a single
+/// catch-all rule so agent mode always does something sensible.
+pub fn load_rules(path: &Path) -> Vec<Rule> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        debug!("no agent rule file at {:?}; using default rule", path);
+        return default_rules();
+    };
+
+    let mut rules = Vec::new();
+    for (lineno, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_rule_line(line) {
+            Some(rule) => rules.push(rule),
+            None => warn!("{:?}:{}: malformed agent rule: {}", path, lineno + 1, line),
+        }
+    }
+
+    if rules.is_empty() {
+        default_rules()
+    } else {
+        rules
+    }
+}
+
+fn parse_rule_line(line: &str) -> Option<Rule> {
+    let (matcher, effect) = line.split_once('=')?;
+    let (kind, pattern) = matcher.split_once(':')?;
+    let (backdrop_str, dark_str) = effect.split_once(',')?;
+
+    let backdrop = match backdrop_str.trim() {
+        "auto" => BackdropKind::Auto,
+        "none" => BackdropKind::None,
+        "mica" => BackdropKind::Mica,
+        "acrylic" => BackdropKind::Acrylic,
+        "tabbed" => BackdropKind::Tabbed,
+        _ => return None,
+    };
+    let dark = match dark_str.trim() {
+        "dark" => true,
+        "light" => false,
+        _ => return None,
+    };
+
+    let mut rule = Rule { match_class: None, match_exe: None, backdrop, dark };
+    match kind.trim() {
+        "class" => rule.match_class = Some(pattern.trim().to_string()),
+        "exe" => rule.match_exe = Some(pattern.trim().to_string()),
+        _ => return None,
+    }
+    Some(rule)
+}
+
+fn get_window_class(hwnd: HWND) -> String {
+    let mut buf = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut buf) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+fn get_window_exe_name(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buf = [0u16; 260];
+        let mut size = buf.len() as u32;
+        let res = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut size);
+        let _ = CloseHandle(process);
+        res.ok()?;
+        let path = String::from_utf16_lossy(&buf[..size as usize]);
+        Path::new(&path).file_name().map(|f| f.to_string_lossy().into_owned())
+    }
+}
+
+fn rule_for(hwnd: HWND, rules: &[Rule]) -> Rule {
+    let class = get_window_class(hwnd);
+    let exe = get_window_exe_name(hwnd);
+
+    for rule in rules {
+        if let Some(pat) = &rule.match_class {
+            if class.eq_ignore_ascii_case(pat) {
+                return rule.clone();
+            }
+        }
+        if let Some(pat) = &rule.match_exe {
+            if exe.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(pat)) {
+                return rule.clone();
+            }
+        }
+    }
+
+    rules
+        .iter()
+        .find(|r| r.match_class.is_none() && r.match_exe.is_none())
+        .cloned()
+        .unwrap_or_else(|| default_rules().remove(0))
+}
+
+fn is_themeable_top_level(hwnd: HWND) -> bool {
+    unsafe {
+        if GetAncestor(hwnd, GA_ROOT) != hwnd {
+            return false;
+        }
+        if !IsWindowVisible(hwnd).as_bool() {
+            return false;
+        }
+        let style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
+        style & WS_CAPTION.0 == WS_CAPTION.0
+    }
+}
+
+extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _thread_id: u32,
+    _time: u32,
+) {
+    if hwnd.0.is_null() || id_object != OBJID_WINDOW.0 || id_child != 0 {
+        return;
+    }
+
+    // HWNDs get recycled after destruction, so evict rather than leaving
+    // a stale entry that would make a future window reusing the same
+    // handle silently fail its own `first_time` check below.
+    if event == EVENT_OBJECT_DESTROY || event == EVENT_OBJECT_HIDE {
+        seen().lock().unwrap().remove(&(hwnd.0 as isize));
+        return;
+    }
+
+    if !is_themeable_top_level(hwnd) {
+        return;
+    }
+
+    let first_time = seen().lock().unwrap().insert(hwnd.0 as isize);
+    if event == EVENT_OBJECT_SHOW && !first_time {
+        return;
+    }
+
+    let rules = RULES.get_or_init(|| load_rules(Path::new("dark_window.rules")));
+    let rule = rule_for(hwnd, rules);
+    debug!("agent: theming {:?} (event {:#x})", hwnd, event);
+    enable_dark_mode(hwnd, rule.dark);
+    crate::uxtheme::allow_dark_mode_for_window(hwnd, rule.dark);
+    apply_backdrop(hwnd, rule.backdrop);
+}
+
+/// Install the out-of-context win event hooks that drive agent mode.
+/// Returns the installed hook handles (empty if every install failed);
+/// pass them to [`uninstall`] on shutdown.
+pub fn install(rule_path: &Path) -> Vec<HWINEVENTHOOK> {
+    RULES.get_or_init(|| load_rules(rule_path));
+
+    let mut hooks = Vec::new();
+    for event in [EVENT_SYSTEM_FOREGROUND, EVENT_OBJECT_SHOW, EVENT_OBJECT_HIDE, EVENT_OBJECT_DESTROY] {
+        let hook = unsafe {
+            SetWinEventHook(event, event, None, Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT)
+        };
+        if hook.0.is_null() {
+            warn!("SetWinEventHook failed for event {:#x}", event);
+        } else {
+            hooks.push(hook);
+        }
+    }
+    hooks
+}
+
+pub fn uninstall(hooks: &[HWINEVENTHOOK]) {
+    for &hook in hooks {
+        unsafe {
+            let _ = UnhookWinEvent(hook);
+        }
+    }
+}
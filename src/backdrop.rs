@@ -0,0 +1,158 @@
+//! Per-OS-version backdrop selection.
+//!
+//! `DWMWA_SYSTEMBACKDROP_TYPE` (attribute 38) only exists starting with the
+//! Windows 11 22H2 Mica revision (build 22621). Earlier Windows 11 builds
+//! need the undocumented `DWMWA_MICA_EFFECT` attribute instead, and
+//! Windows 10 has no DWM-level Mica/Acrylic at all, so it falls back to
+//! the existing `set_window_blur` blur-behind path. [`apply_backdrop`]
+//! hides all of that behind a single call: callers ask for an effect via
+//! [`BackdropKind`] and this module resolves it to whatever the running
+//! build actually supports.
+
+use std::mem;
+use std::sync::OnceLock;
+
+use log::*;
+use windows::core::s;
+use windows::Win32::Foundation::{BOOL, HWND};
+use windows::Win32::Graphics::Dwm::*;
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryA};
+use windows::Win32::System::SystemInformation::OSVERSIONINFOW;
+
+use crate::{enable_blur_behind, set_window_blur, ACCENT_ENABLE_ACRYLICBLURBEHIND};
+
+pub const DWMWA_SYSTEMBACKDROP_TYPE: DWMWINDOWATTRIBUTE = DWMWINDOWATTRIBUTE(38i32);
+const DWMWA_MICA_EFFECT: DWMWINDOWATTRIBUTE = DWMWINDOWATTRIBUTE(1029i32);
+
+#[allow(non_camel_case_types)]
+type DWM_SYSTEMBACKDROP_TYPE = u32;
+
+const DWMSBT_NONE: DWM_SYSTEMBACKDROP_TYPE = 1;
+const DWMSBT_MAINWINDOW: DWM_SYSTEMBACKDROP_TYPE = 2;
+const DWMSBT_TRANSIENTWINDOW: DWM_SYSTEMBACKDROP_TYPE = 3;
+const DWMSBT_TABBEDWINDOW: DWM_SYSTEMBACKDROP_TYPE = 4;
+
+const BUILD_WIN11_21H2: u32 = 22000;
+const BUILD_WIN11_22H2: u32 = 22621;
+
+/// The backdrop effect a caller wants applied to a top-level window,
+/// independent of which underlying DWM mechanism the running OS build
+/// actually supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackdropKind {
+    /// Same effect as [`BackdropKind::Mica`] on every build
+    /// (`DWMSBT_MAINWINDOW` on 22621+).
+    Auto,
+    /// No backdrop; solid fill.
+    None,
+    Mica,
+    Acrylic,
+    Tabbed,
+}
+
+type FnRtlGetVersion = unsafe extern "system" fn(*mut OSVERSIONINFOW) -> i32;
+
+fn rtl_get_version() -> Option<FnRtlGetVersion> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| unsafe {
+        let dll = LoadLibraryA(s!("ntdll.dll")).ok()?;
+        let proc = GetProcAddress(dll, s!("RtlGetVersion"))?;
+        Some(proc as usize)
+    });
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnRtlGetVersion>(addr) })
+}
+
+/// The true OS build number, via `RtlGetVersion`. `GetVersionExA` is
+/// shimmed by the compatibility layer and lies about anything >= Win10
+/// unless the exe carries a manifest declaring support for it.
+pub(crate) fn os_build_number() -> u32 {
+    let Some(rtl_get_version) = rtl_get_version() else {
+        warn!("RtlGetVersion unavailable; assuming pre-Win11 backdrop support");
+        return 0;
+    };
+    unsafe {
+        let mut info = OSVERSIONINFOW {
+            dwOSVersionInfoSize: mem::size_of::<OSVERSIONINFOW>() as u32,
+            ..Default::default()
+        };
+        rtl_get_version(&mut info);
+        info.dwBuildNumber
+    }
+}
+
+fn backdrop_type_for(kind: BackdropKind) -> DWM_SYSTEMBACKDROP_TYPE {
+    match kind {
+        BackdropKind::None => DWMSBT_NONE,
+        BackdropKind::Auto | BackdropKind::Mica => DWMSBT_MAINWINDOW,
+        BackdropKind::Acrylic => DWMSBT_TRANSIENTWINDOW,
+        BackdropKind::Tabbed => DWMSBT_TABBEDWINDOW,
+    }
+}
+
+fn set_systembackdrop_type(hwnd: HWND, backdrop: DWM_SYSTEMBACKDROP_TYPE) -> bool {
+    let value: u32 = backdrop;
+    let res = unsafe {
+        DwmSetWindowAttribute(hwnd, DWMWA_SYSTEMBACKDROP_TYPE, &value as *const u32 as *const _, mem::size_of::<u32>() as u32)
+    };
+    match res {
+        Err(_) => { error!("DWMWA_SYSTEMBACKDROP_TYPE invalid parameter"); false },
+        Ok(_) => true,
+    }
+}
+
+fn set_mica_effect(hwnd: HWND, enable: bool) -> bool {
+    let value = BOOL::from(enable);
+    let res = unsafe {
+        DwmSetWindowAttribute(hwnd, DWMWA_MICA_EFFECT, &value as *const BOOL as *const _, mem::size_of::<BOOL>() as u32)
+    };
+    match res {
+        Err(_) => { error!("DWMWA_MICA_EFFECT invalid parameter"); false },
+        Ok(_) => true,
+    }
+}
+
+fn legacy_acrylic_fallback(hwnd: HWND) -> bool {
+    let margins = MARGINS {
+        cxLeftWidth: -1,
+        cxRightWidth: -1,
+        cyTopHeight: -1,
+        cyBottomHeight: -1,
+    };
+    if let Err(e) = unsafe { DwmExtendFrameIntoClientArea(hwnd, &margins) } {
+        warn!("DwmExtendFrameIntoClientArea failed for {:?}: {e}", hwnd);
+        return false;
+    }
+    set_window_blur(hwnd, ACCENT_ENABLE_ACRYLICBLURBEHIND)
+}
+
+/// Resolve `kind` to whichever DWM mechanism the running OS build
+/// understands and apply it to `hwnd`. Degrades to a solid fill (no-op)
+/// when DWM composition itself is off, since none of these mechanisms
+/// mean anything without it.
+pub fn apply_backdrop(hwnd: HWND, kind: BackdropKind) -> bool {
+    if !crate::dwm_api::composition_enabled() {
+        warn!("DWM composition disabled; falling back to solid fill");
+        return false;
+    }
+
+    let build = os_build_number();
+
+    if build >= BUILD_WIN11_22H2 {
+        if !set_systembackdrop_type(hwnd, backdrop_type_for(kind)) {
+            return false;
+        }
+        return kind == BackdropKind::None || enable_blur_behind(hwnd);
+    }
+
+    if build >= BUILD_WIN11_21H2 {
+        if kind == BackdropKind::None {
+            return true;
+        }
+        return set_mica_effect(hwnd, true) && enable_blur_behind(hwnd);
+    }
+
+    if kind == BackdropKind::None {
+        return true;
+    }
+    legacy_acrylic_fallback(hwnd)
+}
@@ -0,0 +1,166 @@
+//! Lazy-resolved DWM/user32 entry points.
+//!
+//! Several effects in this crate call into functions that `windows-rs`
+//! doesn't expose (`SetWindowCompositionAttribute`) or that have no import
+//! lib at all (undocumented uxtheme ordinals, added alongside this
+//! module). Resolving those via `LoadLibraryA`/`GetProcAddress` on every
+//! call was wasteful and scattered the unsafe transmutes across the
+//! crate. This module resolves each symbol exactly once into a cached
+//! function pointer behind a `OnceLock` and hands back a typed wrapper,
+//! or `None` if the symbol isn't present on the running system.
+
+use std::mem;
+use std::sync::OnceLock;
+
+use windows::core::{s, PCSTR};
+use windows::Win32::Foundation::{BOOL, HWND};
+use windows::Win32::Graphics::Dwm::DwmIsCompositionEnabled;
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryA};
+
+use crate::WINDOWCOMPOSITIONATTRIBDATA;
+
+type FnSetWindowCompositionAttribute = unsafe extern "system" fn(HWND, *mut WINDOWCOMPOSITIONATTRIBDATA) -> BOOL;
+
+/// Resolve `proc` out of `module`, loading the module if needed. `None`
+/// if either the module or the entry point can't be found.
+fn resolve(module: PCSTR, proc: PCSTR) -> Option<usize> {
+    unsafe {
+        let handle = LoadLibraryA(module).ok()?;
+        GetProcAddress(handle, proc).map(|f| f as usize)
+    }
+}
+
+/// Resolve an export by ordinal rather than name, the way the
+/// undocumented uxtheme functions have to be looked up (they carry no
+/// public name in the import lib, only a stable-ish ordinal).
+fn resolve_ordinal(module: PCSTR, ordinal: u16) -> Option<usize> {
+    resolve(module, PCSTR(ordinal as usize as *const u8))
+}
+
+fn set_window_composition_attribute_fn() -> Option<FnSetWindowCompositionAttribute> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve(s!("user32.dll"), s!("SetWindowCompositionAttribute")));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnSetWindowCompositionAttribute>(addr) })
+}
+
+// Undocumented uxtheme.dll ordinals used for dark-mode detection and
+// control theming. `ShouldAppsUseDarkMode` (132), `AllowDarkModeForWindow`
+// (133), `FlushMenuThemes` (136) and `RefreshImmersiveColorPolicyState`
+// (104) have kept the same ordinal and signature since Windows 10 1809;
+// only ordinal 135 changed *meaning* across the 1903 feature update
+// (`AllowDarkModeForApp` -> `SetPreferredAppMode`), so only
+// `set_preferred_app_mode` below branches on
+// `crate::backdrop::os_build_number()`.
+const UXTHEME_SHOULD_APPS_USE_DARK_MODE: u16 = 132;
+const UXTHEME_ALLOW_DARK_MODE_FOR_WINDOW: u16 = 133;
+const UXTHEME_ALLOW_DARK_MODE_FOR_APP: u16 = 135; // pre-1903 (build < 18362)
+const UXTHEME_SET_PREFERRED_APP_MODE: u16 = 135; // 1903+ (build >= 18362)
+const UXTHEME_FLUSH_MENU_THEMES: u16 = 136;
+const UXTHEME_REFRESH_IMMERSIVE_COLOR_POLICY_STATE: u16 = 104;
+
+type FnShouldAppsUseDarkMode = unsafe extern "system" fn() -> BOOL;
+type FnAllowDarkModeForWindow = unsafe extern "system" fn(HWND, BOOL) -> BOOL;
+type FnAllowDarkModeForApp = unsafe extern "system" fn(BOOL) -> BOOL;
+type FnSetPreferredAppMode = unsafe extern "system" fn(i32) -> i32;
+type FnFlushMenuThemes = unsafe extern "system" fn();
+type FnRefreshImmersiveColorPolicyState = unsafe extern "system" fn();
+
+fn should_apps_use_dark_mode_fn() -> Option<FnShouldAppsUseDarkMode> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve_ordinal(s!("uxtheme.dll"), UXTHEME_SHOULD_APPS_USE_DARK_MODE));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnShouldAppsUseDarkMode>(addr) })
+}
+
+fn allow_dark_mode_for_window_fn() -> Option<FnAllowDarkModeForWindow> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve_ordinal(s!("uxtheme.dll"), UXTHEME_ALLOW_DARK_MODE_FOR_WINDOW));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnAllowDarkModeForWindow>(addr) })
+}
+
+fn allow_dark_mode_for_app_fn() -> Option<FnAllowDarkModeForApp> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve_ordinal(s!("uxtheme.dll"), UXTHEME_ALLOW_DARK_MODE_FOR_APP));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnAllowDarkModeForApp>(addr) })
+}
+
+fn set_preferred_app_mode_fn() -> Option<FnSetPreferredAppMode> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve_ordinal(s!("uxtheme.dll"), UXTHEME_SET_PREFERRED_APP_MODE));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnSetPreferredAppMode>(addr) })
+}
+
+fn flush_menu_themes_fn() -> Option<FnFlushMenuThemes> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| resolve_ordinal(s!("uxtheme.dll"), UXTHEME_FLUSH_MENU_THEMES));
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnFlushMenuThemes>(addr) })
+}
+
+fn refresh_immersive_color_policy_state_fn() -> Option<FnRefreshImmersiveColorPolicyState> {
+    static PROC: OnceLock<Option<usize>> = OnceLock::new();
+    let addr = *PROC.get_or_init(|| {
+        resolve_ordinal(s!("uxtheme.dll"), UXTHEME_REFRESH_IMMERSIVE_COLOR_POLICY_STATE)
+    });
+    addr.map(|addr| unsafe { mem::transmute::<usize, FnRefreshImmersiveColorPolicyState>(addr) })
+}
+
+/// `uxtheme!ShouldAppsUseDarkMode` (ordinal 132). `None` if unavailable.
+pub fn should_apps_use_dark_mode() -> Option<bool> {
+    let f = should_apps_use_dark_mode_fn()?;
+    Some(unsafe { f().as_bool() })
+}
+
+/// `uxtheme!AllowDarkModeForWindow` (ordinal 133): opt a single HWND in
+/// or out of dark non-client rendering. `None` if unavailable.
+pub fn allow_dark_mode_for_window(hwnd: HWND, allow: bool) -> Option<bool> {
+    let f = allow_dark_mode_for_window_fn()?;
+    Some(unsafe { f(hwnd, allow.into()).as_bool() })
+}
+
+/// Opt the whole process into dark mode at startup, via whichever of
+/// `AllowDarkModeForApp` (pre-1903) or `SetPreferredAppMode` (1903+) the
+/// running build exposes. `force` maps to `ForceDark`; otherwise
+/// `AllowDark`. `None` if neither ordinal resolved.
+pub fn set_preferred_app_mode(build: u32, force: bool) -> Option<bool> {
+    const BUILD_1903: u32 = 18362;
+    const ALLOW_DARK: i32 = 1;
+    const FORCE_DARK: i32 = 2;
+
+    if build >= BUILD_1903 {
+        let f = set_preferred_app_mode_fn()?;
+        unsafe { f(if force { FORCE_DARK } else { ALLOW_DARK }) };
+        Some(true)
+    } else {
+        let f = allow_dark_mode_for_app_fn()?;
+        Some(unsafe { f(true.into()).as_bool() })
+    }
+}
+
+/// `uxtheme!FlushMenuThemes` (ordinal 136). `None` if unavailable.
+pub fn flush_menu_themes() -> Option<()> {
+    let f = flush_menu_themes_fn()?;
+    unsafe { f() };
+    Some(())
+}
+
+/// `uxtheme!RefreshImmersiveColorPolicyState` (ordinal 104). `None` if
+/// unavailable.
+pub fn refresh_immersive_color_policy_state() -> Option<()> {
+    let f = refresh_immersive_color_policy_state_fn()?;
+    unsafe { f() };
+    Some(())
+}
+
+/// `true` if DWM composition — and therefore any blur/backdrop effect —
+/// is active on the desktop. `false` under Remote Desktop sessions with
+/// composition disabled, reduced-performance mode, etc; callers should
+/// degrade to a solid fill rather than calling into the effect APIs.
+pub fn composition_enabled() -> bool {
+    unsafe { DwmIsCompositionEnabled().map(|v| v.as_bool()).unwrap_or(false) }
+}
+
+/// Typed wrapper over the undocumented `user32!SetWindowCompositionAttribute`.
+/// Returns `None` if the entry point isn't present on this system.
+pub fn set_window_composition_attribute(hwnd: HWND, data: &mut WINDOWCOMPOSITIONATTRIBDATA) -> Option<bool> {
+    let set_wnd_composition_attr = set_window_composition_attribute_fn()?;
+    Some(unsafe { set_wnd_composition_attr(hwnd, data).as_bool() })
+}
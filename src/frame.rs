@@ -0,0 +1,123 @@
+//! Borderless custom-frame support.
+//!
+//! Removes the native title bar and borders by answering `WM_NCCALCSIZE`
+//! with a zero client-area inset, while still answering `WM_NCHITTEST`
+//! ourselves so native drag-move, edge/corner resize, and Aero snap keep
+//! working against a window that, as far as DWM is concerned, still has
+//! a caption (so it still gets the drop shadow and snap animations).
+//! Only wired up when [`is_borderless`] is enabled.
+
+use std::sync::OnceLock;
+
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Dwm::{DwmExtendFrameIntoClientArea, MARGINS};
+use windows::Win32::UI::HiDpi::GetDpiForWindow;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+/// Logical-pixel height of the custom title strip reserved at the top of
+/// the client area.
+const CAPTION_HEIGHT_DIP: i32 = 32;
+/// Logical-pixel width of the hit-test band along each edge that still
+/// resizes the window.
+const RESIZE_BORDER_DIP: i32 = 8;
+
+fn scale(hwnd: HWND, dip: i32) -> i32 {
+    let dpi = unsafe { GetDpiForWindow(hwnd) } as i32;
+    dip * dpi / 96
+}
+
+/// DPI-scaled height of the custom title strip, as used by
+/// [`handle_nchittest`] to map it to `HTCAPTION`.
+pub fn caption_height(hwnd: HWND) -> i32 {
+    scale(hwnd, CAPTION_HEIGHT_DIP)
+}
+
+/// `true` if this process was launched in borderless custom-frame mode.
+pub fn is_borderless() -> bool {
+    static BORDERLESS: OnceLock<bool> = OnceLock::new();
+    *BORDERLESS.get_or_init(|| std::env::args().any(|arg| arg == "--borderless"))
+}
+
+/// Keep `DwmExtendFrameIntoClientArea` active so the borderless window
+/// still gets the native drop shadow; a 1px bottom extension is the
+/// usual trick to turn the shadow on without reserving any visible
+/// non-client area.
+pub fn enable_shadow(hwnd: HWND) {
+    let margins = MARGINS { cxLeftWidth: 0, cxRightWidth: 0, cyTopHeight: 0, cyBottomHeight: 1 };
+    unsafe { let _ = DwmExtendFrameIntoClientArea(hwnd, &margins); };
+}
+
+/// The horizontal/vertical overhang Windows adds to a maximized
+/// borderless window on every side, so it doesn't bleed onto
+/// neighbouring monitors: the standard resize frame thickness plus the
+/// (historically separate) padded-border fudge, per system metrics
+/// rather than this crate's own hit-test band width.
+fn maximized_overhang() -> (i32, i32) {
+    unsafe {
+        let padding = GetSystemMetrics(SM_CXPADDEDBORDER);
+        (GetSystemMetrics(SM_CXSIZEFRAME) + padding, GetSystemMetrics(SM_CYSIZEFRAME) + padding)
+    }
+}
+
+/// Handle `WM_NCCALCSIZE`: remove the native frame by leaving the
+/// proposed client rect untouched, except when maximized, where Windows
+/// outsets the proposed window rect by the resize-frame size on all four
+/// sides before this runs, and that overhang has to be clamped back in
+/// or the edges get clipped off / bleed onto neighbouring monitors.
+pub fn handle_nccalcsize(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if wparam.0 == 0 {
+        return LRESULT(0);
+    }
+
+    let maximized = unsafe { IsZoomed(hwnd).as_bool() };
+    if maximized {
+        let params = unsafe { &mut *(lparam.0 as *mut NCCALCSIZE_PARAMS) };
+        let rect = &mut params.rgrc[0];
+        let (cx, cy) = maximized_overhang();
+        rect.left += cx;
+        rect.right -= cx;
+        rect.top += cy;
+        rect.bottom -= cy;
+    }
+
+    LRESULT(0)
+}
+
+/// Handle `WM_NCHITTEST`: map the outer edge band to resize handles and
+/// the reserved caption strip to `HTCAPTION`, so the window still drags
+/// and edge/corner-resizes with no native frame drawn.
+pub fn handle_nchittest(hwnd: HWND, lparam: LPARAM) -> LRESULT {
+    let cursor = POINT {
+        x: (lparam.0 & 0xFFFF) as i16 as i32,
+        y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+    };
+
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); };
+
+    if unsafe { IsZoomed(hwnd).as_bool() } {
+        // A maximized window doesn't resize; only the caption strip matters.
+        return if cursor.y < rect.top + caption_height(hwnd) { LRESULT(HTCAPTION as isize) } else { LRESULT(HTCLIENT as isize) };
+    }
+
+    let border = scale(hwnd, RESIZE_BORDER_DIP);
+    let on_left = cursor.x < rect.left + border;
+    let on_right = cursor.x >= rect.right - border;
+    let on_top = cursor.y < rect.top + border;
+    let on_bottom = cursor.y >= rect.bottom - border;
+
+    let hit = match (on_left, on_right, on_top, on_bottom) {
+        (true, _, true, _) => HTTOPLEFT,
+        (_, true, true, _) => HTTOPRIGHT,
+        (true, _, _, true) => HTBOTTOMLEFT,
+        (_, true, _, true) => HTBOTTOMRIGHT,
+        (true, false, false, false) => HTLEFT,
+        (false, true, false, false) => HTRIGHT,
+        (false, false, true, false) => HTTOP,
+        (false, false, false, true) => HTBOTTOM,
+        _ if cursor.y < rect.top + caption_height(hwnd) => HTCAPTION,
+        _ => HTCLIENT,
+    };
+
+    LRESULT(hit as isize)
+}
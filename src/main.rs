@@ -3,7 +3,7 @@
 
 use std::ffi::c_void;
 use std::{fs::File, path::Path};
-use std::{mem, ptr};
+use std::ptr;
 use std::sync::atomic::{ AtomicBool, Ordering };
 
 use log::*;
@@ -15,14 +15,25 @@ use windows::{
     core::*,
     Win32::Foundation::*, Win32::System::Registry::*,
     Win32::Graphics::Dwm::*, Win32::Graphics::Gdi::*, Win32::UI::HiDpi::*, Win32::UI::Controls::*,
-    Win32::System::LibraryLoader::{GetModuleHandleA, GetProcAddress, LoadLibraryA},
+    Win32::System::LibraryLoader::GetModuleHandleA,
     Win32::UI::WindowsAndMessaging::*,
 };
 
+mod agent;
+mod backdrop;
+mod dwm_api;
+mod frame;
+mod uxtheme;
+
 static IS_DARK_MODE: AtomicBool = AtomicBool::new(false);
 static IS_FIRST_PAINT: AtomicBool = AtomicBool::new(true);
 
 fn check_dark_mode() {
+    if let Some(dark) = uxtheme::should_use_dark_mode() {
+        IS_DARK_MODE.store(dark, Ordering::Relaxed);
+        return;
+    }
+
     unsafe {
         let mut key = HKEY::default();
         let _ = RegOpenKeyExA(HKEY_CURRENT_USER, s!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"), Some(0), KEY_READ, &mut key);
@@ -38,37 +49,17 @@ fn check_dark_mode() {
     }
 }
 
-fn enable_dark_mode(hwnd: HWND, enable: bool) {
+fn enable_dark_mode(hwnd: HWND, enable: bool) -> bool {
     let value:u32 = enable as u32;
-    unsafe {
-        DwmSetWindowAttribute(hwnd, DWMWA_USE_IMMERSIVE_DARK_MODE, &value as *const u32 as *const _, std::mem::size_of::<u32>() as u32).unwrap();
-    }
-}
-
-pub const DWMWA_SYSTEMBACKDROP_TYPE: DWMWINDOWATTRIBUTE = DWMWINDOWATTRIBUTE(38i32);
-
-#[allow(non_camel_case_types)]
-type DWM_SYSTEMBACKDROP_TYPE = u32;
-
-const DWMSBT_AUTO: DWM_SYSTEMBACKDROP_TYPE = 0;
-const DWMSBT_NONE: DWM_SYSTEMBACKDROP_TYPE = 1;
-const DWMSBT_MAINWINDOW: DWM_SYSTEMBACKDROP_TYPE = 2;
-const DWMSBT_TRANSIENTWINDOW: DWM_SYSTEMBACKDROP_TYPE = 3;
-const DWMSBT_TABBEDWINDOW: DWM_SYSTEMBACKDROP_TYPE = 4;
-
-fn set_backdrop_type(hwnd: HWND, backdrop: DWM_SYSTEMBACKDROP_TYPE) -> bool {
-    let value:u32 = backdrop as u32;
     let res = unsafe {
-        DwmSetWindowAttribute(hwnd, DWMWA_SYSTEMBACKDROP_TYPE, &value as *const u32 as *const _, std::mem::size_of::<u32>() as u32)
+        DwmSetWindowAttribute(hwnd, DWMWA_USE_IMMERSIVE_DARK_MODE, &value as *const u32 as *const _, std::mem::size_of::<u32>() as u32)
     };
-    return match res {
-        Err(_) => { error!("DWMWA_SYSTEMBACKDROP_TYPE invalid parameter"); false },
+    match res {
+        Err(e) => { warn!("DWMWA_USE_IMMERSIVE_DARK_MODE failed for {:?}: {e}", hwnd); false },
         Ok(_) => true,
-    };
+    }
 }
 
-type FnSetWindowCompositionAttribute = unsafe extern "system" fn(HWND, *mut WINDOWCOMPOSITIONATTRIBDATA) -> BOOL;
-
 #[allow(clippy::upper_case_acronyms)]
 type WINDOWCOMPOSITIONATTRIB = u32;
 const WCA_ACCENT_POLICY: WINDOWCOMPOSITIONATTRIB = 19;
@@ -112,38 +103,33 @@ fn enable_blur_behind(hwnd: HWND) -> bool {
         fTransitionOnMaximized: false.into(),
     };
 
-    unsafe { DwmEnableBlurBehindWindow(hwnd, &bb).unwrap(); };
-    true
+    match unsafe { DwmEnableBlurBehindWindow(hwnd, &bb) } {
+        Err(e) => { warn!("DwmEnableBlurBehindWindow failed for {:?}: {e}", hwnd); false },
+        Ok(_) => true,
+    }
 }
 
 fn set_window_blur(hwnd: HWND, accent_state: ACCENT_STATE) -> bool {
-    unsafe {
-        let dll_handle = LoadLibraryA(s!("user32.dll"));
-        if dll_handle.is_err() {
-            println!("Failed to load DLL: {}", dll_handle.err().unwrap());
-            return false;
-        }
-        let function = GetProcAddress(dll_handle.unwrap(), s!("SetWindowCompositionAttribute"));
-        if function.is_none() {
-            println!("SetWindowCompositionAttribute entry point not found!");
-            return false;
-        }
-        let mut policy = ACCENT_POLICY {
-            AccentState: accent_state,
-            AccentFlags: 0,
-            GradientColor: (0x40 << 24) | (0x2f2f2f & 0xFFFFFF),
-            AnimationId: 0
-        };
-
-        let mut data = WINDOWCOMPOSITIONATTRIBDATA {
-            Attrib: WCA_ACCENT_POLICY,
-            pvData: &mut policy as *mut _ as _,
-            cbData: std::mem::size_of_val(&policy) as _,
-        };
+    let mut policy = ACCENT_POLICY {
+        AccentState: accent_state,
+        AccentFlags: 0,
+        GradientColor: (0x40 << 24) | (0x2f2f2f & 0xFFFFFF),
+        AnimationId: 0
+    };
 
-        let set_wnd_composition_attr: FnSetWindowCompositionAttribute = mem::transmute(function);
-        return set_wnd_composition_attr(hwnd, &mut data).as_bool();
+    let mut data = WINDOWCOMPOSITIONATTRIBDATA {
+        Attrib: WCA_ACCENT_POLICY,
+        pvData: &mut policy as *mut _ as _,
+        cbData: std::mem::size_of_val(&policy) as _,
     };
+
+    match dwm_api::set_window_composition_attribute(hwnd, &mut data) {
+        Some(ok) => ok,
+        None => {
+            warn!("SetWindowCompositionAttribute entry point not found!");
+            false
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -185,6 +171,7 @@ fn main() -> Result<()> {
         ]).unwrap();
 
         SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE).unwrap();
+        uxtheme::init();
         check_dark_mode();
 
         let class_name = fname.to_string_lossy() + "\0";
@@ -227,11 +214,17 @@ fn main() -> Result<()> {
             None,
         );
 
+        let agent_hooks = std::env::args().any(|arg| arg == "--agent")
+            .then(|| agent::install(Path::new("dark_window.rules")))
+            .unwrap_or_default();
+
         let mut message = MSG::default();
         while GetMessageA(&mut message, None, 0, 0).into() {
             DispatchMessageA(&message);
         }
 
+        agent::uninstall(&agent_hooks);
+
         Ok(())
     }
 }
@@ -240,21 +233,17 @@ extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: L
     unsafe {
         match message {
             WM_CREATE => {
-                enable_dark_mode(window, IS_DARK_MODE.load(Ordering::Relaxed));
-                if !set_backdrop_type(window, DWMSBT_TRANSIENTWINDOW) {
-                    let margins = MARGINS {
-                        cxLeftWidth: -1,
-                        cxRightWidth: -1,
-                        cyTopHeight: -1,
-                        cyBottomHeight: -1
-                    };
-                    DwmExtendFrameIntoClientArea(window, &margins).unwrap();
-                    set_window_blur(window, ACCENT_ENABLE_BLURBEHIND);
-                } else {
-                    enable_blur_behind(window);
+                let dark = IS_DARK_MODE.load(Ordering::Relaxed);
+                enable_dark_mode(window, dark);
+                uxtheme::allow_dark_mode_for_window(window, dark);
+                backdrop::apply_backdrop(window, backdrop::BackdropKind::Auto);
+                if frame::is_borderless() {
+                    frame::enable_shadow(window);
                 }
                 LRESULT(0)
             }
+            WM_NCCALCSIZE if frame::is_borderless() => frame::handle_nccalcsize(window, wparam, lparam),
+            WM_NCHITTEST if frame::is_borderless() => frame::handle_nchittest(window, lparam),
             WM_ERASEBKGND => {
                 let hdc = HDC(wparam.0 as _);
                 let mut rect = RECT::default();
@@ -303,8 +292,11 @@ extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: L
             }
             WM_SETTINGCHANGE => {
                 debug!("WM_SETTINGCHANGE");
+                uxtheme::refresh_color_policy();
                 check_dark_mode();
-                enable_dark_mode(window, IS_DARK_MODE.load(Ordering::Relaxed));
+                let dark = IS_DARK_MODE.load(Ordering::Relaxed);
+                enable_dark_mode(window, dark);
+                uxtheme::allow_dark_mode_for_window(window, dark);
                 let _ = InvalidateRect(Some(window), None, true);
                 LRESULT(0)
             }
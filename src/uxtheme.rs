@@ -0,0 +1,59 @@
+//! High-level dark-mode integration built on uxtheme.dll's undocumented
+//! ordinals (resolved lazily via [`crate::dwm_api`]).
+//!
+//! `DwmSetWindowAttribute(DWMWA_USE_IMMERSIVE_DARK_MODE)` only darkens the
+//! non-client frame; it does nothing for child controls like scrollbars,
+//! buttons and edit boxes, and nothing opts the *process* itself into
+//! dark mode with the shell. This module fixes both: [`init`] opts the
+//! process in at startup, [`allow_dark_mode_for_window`] opts a window
+//! and its children in per-HWND, and [`refresh_color_policy`] refreshes
+//! the shell's cached color policy on `WM_SETTINGCHANGE`.
+
+use windows::core::w;
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+use windows::Win32::UI::Controls::SetWindowTheme;
+use windows::Win32::UI::WindowsAndMessaging::EnumChildWindows;
+
+use crate::backdrop::os_build_number;
+use crate::dwm_api;
+
+/// `true`/`false` per `uxtheme!ShouldAppsUseDarkMode`, or `None` if the
+/// ordinal isn't available on this build — callers should fall back to
+/// reading the `AppsUseLightTheme` registry value directly in that case.
+pub fn should_use_dark_mode() -> Option<bool> {
+    dwm_api::should_apps_use_dark_mode()
+}
+
+/// Opt this process into dark-mode-aware theming. Call once at startup,
+/// before creating any windows.
+pub fn init() {
+    let build = os_build_number();
+    if dwm_api::set_preferred_app_mode(build, false).is_none() {
+        log::warn!("uxtheme dark mode ordinals unavailable; child controls will stay light");
+    }
+}
+
+extern "system" fn theme_child(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let theme = if lparam.0 != 0 { w!("DarkMode_Explorer") } else { w!("Explorer") };
+    unsafe {
+        let _ = SetWindowTheme(hwnd, theme, None);
+    }
+    true.into()
+}
+
+/// Opt `hwnd` and its child controls (scrollbars, buttons, edit boxes)
+/// into (or out of) dark rendering. Call after
+/// `DWMWA_USE_IMMERSIVE_DARK_MODE` so the frame and its controls agree.
+pub fn allow_dark_mode_for_window(hwnd: HWND, enable: bool) {
+    dwm_api::allow_dark_mode_for_window(hwnd, enable);
+    unsafe {
+        let _ = EnumChildWindows(Some(hwnd), Some(theme_child), LPARAM(enable as isize));
+    }
+}
+
+/// Refresh the shell's cached dark/light color policy. Call on
+/// `WM_SETTINGCHANGE`, before repainting.
+pub fn refresh_color_policy() {
+    dwm_api::refresh_immersive_color_policy_state();
+    dwm_api::flush_menu_themes();
+}